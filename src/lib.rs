@@ -1,5 +1,6 @@
 use std::cmp::*;
-use std::io::Cursor;
+use std::io;
+use std::io::{Cursor, Read, Write};
 use bitflags::bitflags;
 use bytes::{Buf, BufMut, BytesMut};
 use bytes_varint::{VarIntError, VarIntSupport, VarIntSupportMut};
@@ -14,10 +15,23 @@ bitflags! {
     struct ChunkFlags: u32 {
         const None = 0x00;
         const Compressed = 0x01;
+        /// Chunk was compressed with up to `LINK_WINDOW` bytes of the preceding plaintext as an LZ4 dictionary.
         const Passes = 0x02;
+        const Checksum = 0x04;
     }
 }
 
+// FNV-1a over the on-wire (possibly compressed) payload, checked before it is trusted.
+fn chunk_checksum(data: &[u8]) -> u32
+{
+    let mut hash: u32 = 0x811c9dc5;
+    for &b in data {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
 pub fn decode_stream(mut data: Cursor<&mut [u8]>, max_output: usize) -> Result<Vec<u8>, DSError>
 {
     let out_size = calc_dc_size(&mut data)?;
@@ -44,8 +58,27 @@ pub(crate) struct StreamDecodeState<'a>
 }
 
 pub fn encode_stream(data: &mut impl Buf) -> Result<Vec<u8>, DSError>
+{
+    encode_stream_with_options(data, EncodeOptions::default())
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncodeOptions
+{
+    pub checksum: bool,
+    /// Compress each chunk using up to `LINK_WINDOW` bytes of the preceding plaintext
+    /// (which may span several earlier chunks) as an LZ4 dictionary, improving ratio
+    /// across block boundaries.
+    pub linked: bool,
+}
+
+/// Size of the sliding plaintext window kept as a dictionary in linked mode.
+pub const LINK_WINDOW: usize = 64 * 1024;
+
+pub fn encode_stream_with_options(data: &mut impl Buf, options: EncodeOptions) -> Result<Vec<u8>, DSError>
 {
     let mut result: BytesMut = BytesMut::new();
+    let mut window: Vec<u8> = Vec::new();
 
     let mut buffer: &[u8];
 
@@ -56,7 +89,17 @@ pub fn encode_stream(data: &mut impl Buf) -> Result<Vec<u8>, DSError>
         }
         let len: usize = min(data.remaining(), BLOCKSIZE);
         buffer = &data.chunk()[0..len];
-        write_chunk(&mut result, buffer);
+        let dict: Option<&[u8]> = if options.linked && !window.is_empty() { Some(&window) } else { None };
+        write_chunk(&mut result, buffer, options.checksum, dict);
+        if options.linked
+        {
+            window.extend_from_slice(buffer);
+            if window.len() > LINK_WINDOW
+            {
+                let excess = window.len() - LINK_WINDOW;
+                window.drain(0..excess);
+            }
+        }
         data.advance(len);
     }
     Ok(result.to_vec())
@@ -68,13 +111,17 @@ pub enum DSError
     LZ4(DecompressError),
     CorruptedOverflow,
     Overflow(usize, usize),
-    VarintFail
+    VarintFail,
+    ChecksumMismatch { expected: u32, actual: u32 },
+    LinkedChunkUnsupported
 }
 pub enum ChunkResult
 {
     Overflow,
     VarintFail,
-    LZ4Fail(DecompressError)
+    LZ4Fail(DecompressError),
+    ChecksumMismatch { expected: u32, actual: u32 },
+    LinkedChunkUnsupported
 }
 
 impl From<VarIntError> for ChunkResult
@@ -93,7 +140,9 @@ impl From<ChunkResult> for DSError
         match value {
             Overflow => CorruptedOverflow,
             VarintFail => DSError::VarintFail,
-            LZ4Fail(ex) => DSError::LZ4(ex)
+            LZ4Fail(ex) => DSError::LZ4(ex),
+            ChunkResult::ChecksumMismatch { expected, actual } => DSError::ChecksumMismatch { expected, actual },
+            ChunkResult::LinkedChunkUnsupported => DSError::LinkedChunkUnsupported
         }
     }
 }
@@ -125,6 +174,11 @@ fn calc_dc_size(data: &mut Cursor<&mut [u8]>) -> Result<usize, ChunkResult>
             } else {
                 original_length
             };
+        if flags.contains(ChunkFlags::Checksum)
+        {
+            if data.remaining() < 4 { return Err(Overflow) }
+            data.advance(4);
+        }
         if length > data.remaining()
         {
             return Err(Overflow);
@@ -153,15 +207,40 @@ fn get_chunk(state: &mut StreamDecodeState) -> Result<bool, ChunkResult>
         {
             original_length
         };
+    let expected_checksum: Option<u32> =
+        if flags.contains(ChunkFlags::Checksum)
+        {
+            Some(state.input.get_u32())
+        } else {
+            None
+        };
     if length > state.input.remaining() || original_length > state.output.remaining()
     {
         return Err(Overflow);
     }
     let cv = &state.input.chunk()[..length];
+    if let Some(expected) = expected_checksum
+    {
+        let actual = chunk_checksum(cv);
+        if actual != expected
+        {
+            return Err(ChunkResult::ChecksumMismatch { expected, actual });
+        }
+    }
     let pos = state.output.position() as usize;
-    let out = &mut state.output.get_mut()[pos..original_length + pos];
+    let linked = flags.contains(ChunkFlags::Passes);
+    let dict_start = pos.saturating_sub(LINK_WINDOW);
+    let (head, tail) = state.output.get_mut().split_at_mut(pos);
+    let dict = &head[dict_start..];
+    let out = &mut tail[..original_length];
     if is_compressed {
-        if lz4_flex::block::decompress_into(cv, out)? != original_length
+        let written = if linked
+        {
+            lz4_flex::block::decompress_into_with_dict(cv, out, dict)?
+        } else {
+            lz4_flex::block::decompress_into(cv, out)?
+        };
+        if written != original_length
         {
             return Err(Overflow)
         }
@@ -177,11 +256,850 @@ fn get_chunk(state: &mut StreamDecodeState) -> Result<bool, ChunkResult>
 
 pub const BLOCKSIZE: usize = 1024*1024;
 
-pub fn write_chunk(data: &mut BytesMut, input: &[u8])
+pub fn write_chunk(data: &mut BytesMut, input: &[u8], emit_checksum: bool, dict: Option<&[u8]>)
 {
-    data.put_u64_varint(0x01);
+    let comp: Vec<u8> = match dict {
+        Some(d) => lz4_flex::block::compress_with_dict(input, d),
+        None => lz4_flex::block::compress(input)
+    };
+    let mut flags = ChunkFlags::Compressed;
+    if emit_checksum
+    {
+        flags |= ChunkFlags::Checksum;
+    }
+    if dict.is_some()
+    {
+        flags |= ChunkFlags::Passes;
+    }
+    data.put_u64_varint(flags.bits() as u64);
     data.put_u64_varint(input.len() as u64);
-    let comp: Vec<u8> = lz4_flex::block::compress(input);
     data.put_u64_varint(comp.len() as u64);
+    if emit_checksum
+    {
+        data.put_u32(chunk_checksum(&comp));
+    }
     data.put(comp.as_slice());
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkIndexEntry
+{
+    pub input_pos: usize,
+    pub output_pos: usize,
+    pub original_length: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamIndex
+{
+    pub entries: Vec<ChunkIndexEntry>,
+}
+
+impl StreamIndex
+{
+    pub fn to_bytes(&self) -> BytesMut
+    {
+        let mut out = BytesMut::new();
+        out.put_u64_varint(self.entries.len() as u64);
+        for entry in &self.entries
+        {
+            out.put_u64_varint(entry.input_pos as u64);
+            out.put_u64_varint(entry.output_pos as u64);
+            out.put_u64_varint(entry.original_length as u64);
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &mut impl Buf) -> Result<StreamIndex, DSError>
+    {
+        let count = data.get_u64_varint().map_err(|_| DSError::VarintFail)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count
+        {
+            let input_pos = data.get_u64_varint().map_err(|_| DSError::VarintFail)? as usize;
+            let output_pos = data.get_u64_varint().map_err(|_| DSError::VarintFail)? as usize;
+            let original_length = data.get_u64_varint().map_err(|_| DSError::VarintFail)? as usize;
+            entries.push(ChunkIndexEntry { input_pos, output_pos, original_length });
+        }
+        Ok(StreamIndex { entries })
+    }
+}
+
+fn walk_chunk_entries(data: &mut Cursor<&mut [u8]>) -> Result<Vec<ChunkIndexEntry>, DSError>
+{
+    let mut entries = Vec::new();
+    let mut output_pos: usize = 0;
+    loop {
+        if data.remaining() == 0
+        {
+            return Ok(entries)
+        }
+        let input_pos = data.position() as usize;
+        let flags_raw = data.get_u64_varint().map_err(|_| DSError::VarintFail)? as u32;
+        let flags: ChunkFlags = ChunkFlags::from_bits_retain(flags_raw);
+        let is_compressed = flags.contains(ChunkFlags::Compressed);
+        let original_length = data.get_u64_varint().map_err(|_| DSError::VarintFail)? as usize;
+        let length: usize =
+            if is_compressed
+            {
+                data.get_u64_varint().map_err(|_| DSError::VarintFail)? as usize
+            } else {
+                original_length
+            };
+        if flags.contains(ChunkFlags::Checksum)
+        {
+            if data.remaining() < 4 { return Err(DSError::CorruptedOverflow) }
+            data.advance(4);
+        }
+        if length > data.remaining()
+        {
+            return Err(DSError::CorruptedOverflow);
+        }
+        entries.push(ChunkIndexEntry { input_pos, output_pos, original_length });
+        output_pos = match output_pos.checked_add(original_length) {
+            None => return Err(DSError::CorruptedOverflow),
+            Some(x) => x
+        };
+        data.advance(length);
+    }
+}
+
+pub fn build_index(mut data: Cursor<&mut [u8]>) -> Result<StreamIndex, DSError>
+{
+    let prev_pos = data.position();
+    let entries = walk_chunk_entries(&mut data)?;
+    data.set_position(prev_pos);
+    Ok(StreamIndex { entries })
+}
+
+fn decode_indexed_chunk(input: &[u8], entry: &ChunkIndexEntry, out: &mut [u8]) -> Result<(), ChunkResult>
+{
+    if entry.input_pos > input.len()
+    {
+        return Err(Overflow);
+    }
+    let mut header: &[u8] = &input[entry.input_pos..];
+    let flags_raw = header.get_u64_varint()? as u32;
+    let flags: ChunkFlags = ChunkFlags::from_bits_retain(flags_raw);
+    let is_compressed = flags.contains(ChunkFlags::Compressed);
+    let original_length = header.get_u64_varint()? as usize;
+    let length: usize =
+        if is_compressed
+        {
+            header.get_u64_varint()? as usize
+        } else {
+            original_length
+        };
+    let expected_checksum: Option<u32> =
+        if flags.contains(ChunkFlags::Checksum)
+        {
+            if header.remaining() < 4 { return Err(Overflow) }
+            Some(header.get_u32())
+        } else {
+            None
+        };
+    if length > header.remaining() || original_length != out.len()
+    {
+        return Err(Overflow);
+    }
+    if flags.contains(ChunkFlags::Passes)
+    {
+        return Err(ChunkResult::LinkedChunkUnsupported);
+    }
+    let cv = &header.chunk()[..length];
+    if let Some(expected) = expected_checksum
+    {
+        let actual = chunk_checksum(cv);
+        if actual != expected
+        {
+            return Err(ChunkResult::ChecksumMismatch { expected, actual });
+        }
+    }
+    if is_compressed {
+        if lz4_flex::block::decompress_into(cv, out)? != original_length
+        {
+            return Err(Overflow)
+        }
+    }
+    else
+    {
+        out.copy_from_slice(cv);
+    };
+    Ok(())
+}
+
+pub fn decode_range(data: Cursor<&mut [u8]>, index: &StreamIndex, range: std::ops::Range<usize>) -> Result<Vec<u8>, DSError>
+{
+    if range.start >= range.end
+    {
+        return Ok(Vec::new());
+    }
+    let input: &[u8] = data.get_ref();
+    let total = index.entries.last().map_or(0, |e| e.output_pos + e.original_length);
+    if range.end > total
+    {
+        return Err(DSError::Overflow(range.end, total));
+    }
+    let first = index.entries.partition_point(|e| e.output_pos + e.original_length <= range.start);
+    let base = index.entries[first].output_pos;
+    let mut span_end = base;
+    let mut chunks: Vec<&ChunkIndexEntry> = Vec::new();
+    for entry in &index.entries[first..]
+    {
+        if entry.output_pos >= range.end
+        {
+            break;
+        }
+        span_end = entry.output_pos + entry.original_length;
+        chunks.push(entry);
+    }
+    let mut buf = vec![0u8; span_end - base];
+    for entry in chunks
+    {
+        let offset = entry.output_pos - base;
+        decode_indexed_chunk(input, entry, &mut buf[offset..offset + entry.original_length])?;
+    }
+    let start = range.start - base;
+    let end = range.end - base;
+    Ok(buf[start..end].to_vec())
+}
+
+#[cfg(feature = "parallel")]
+fn thread_count(requested: usize) -> usize
+{
+    requested.max(1)
+}
+
+#[cfg(feature = "parallel")]
+pub fn encode_stream_parallel(data: &[u8], threads: usize) -> Result<Vec<u8>, DSError>
+{
+    let blocks: Vec<&[u8]> = data.chunks(BLOCKSIZE).collect();
+    let mut outputs: Vec<BytesMut> = Vec::with_capacity(blocks.len());
+    outputs.resize_with(blocks.len(), BytesMut::new);
+
+    let worker_count = thread_count(threads).min(blocks.len().max(1));
+    let job_size = (blocks.len() + worker_count - 1) / worker_count.max(1);
+    let job_size = job_size.max(1);
+
+    std::thread::scope(|scope| {
+        for (worker_id, out_jobs) in outputs.chunks_mut(job_size).enumerate()
+        {
+            let start = worker_id * job_size;
+            let in_jobs = &blocks[start..start + out_jobs.len()];
+            scope.spawn(move || {
+                for (block, out) in in_jobs.iter().zip(out_jobs.iter_mut())
+                {
+                    write_chunk(out, block, false, None);
+                }
+            });
+        }
+    });
+
+    let mut result = BytesMut::new();
+    for out in outputs
+    {
+        result.unsplit(out);
+    }
+    Ok(result.to_vec())
+}
+
+#[cfg(feature = "parallel")]
+pub fn decode_stream_parallel(mut data: Cursor<&mut [u8]>, max_output: usize, threads: usize) -> Result<Vec<u8>, DSError>
+{
+    let entries = walk_chunk_entries(&mut data)?;
+    let out_size = entries.last().map_or(0, |e| e.output_pos + e.original_length);
+    if out_size > max_output
+    {
+        return Err(DSError::Overflow(out_size, max_output));
+    }
+
+    let input: &[u8] = data.get_ref();
+    let mut output = vec![0u8; out_size];
+    let mut slices: Vec<&mut [u8]> = Vec::with_capacity(entries.len());
+    let mut rest: &mut [u8] = &mut output;
+    for entry in &entries
+    {
+        let (slice, remainder) = rest.split_at_mut(entry.original_length);
+        slices.push(slice);
+        rest = remainder;
+    }
+
+    let worker_count = thread_count(threads).min(entries.len().max(1));
+    let job_size = (entries.len() + worker_count - 1) / worker_count.max(1);
+    let job_size = job_size.max(1);
+
+    let result: Result<(), ChunkResult> = std::thread::scope(|scope| {
+        let mut handles = Vec::new();
+        let mut entry_start = 0;
+        for out_jobs in slices.chunks_mut(job_size)
+        {
+            let job_entries = &entries[entry_start..entry_start + out_jobs.len()];
+            entry_start += out_jobs.len();
+            handles.push(scope.spawn(move || {
+                for (entry, out) in job_entries.iter().zip(out_jobs.iter_mut())
+                {
+                    decode_indexed_chunk(input, entry, out)?;
+                }
+                Ok::<(), ChunkResult>(())
+            }));
+        }
+        for handle in handles
+        {
+            handle.join().unwrap()?;
+        }
+        Ok(())
+    });
+    result?;
+    Ok(output)
+}
+#[derive(Debug, Clone, Copy)]
+pub struct CdcParams
+{
+    pub min_size: usize,
+    pub avg_size: usize,
+    pub max_size: usize,
+}
+
+impl Default for CdcParams
+{
+    fn default() -> Self
+    {
+        CdcParams { min_size: 256 * 1024, avg_size: 1024 * 1024, max_size: 4 * 1024 * 1024 }
+    }
+}
+
+fn cdc_mask(bits: u32) -> u64
+{
+    if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 }
+}
+
+/// Number of bytes immediately before `min_size` used to warm up the rolling Gear hash,
+/// so the first cut-point test at `min_size` sees a full sliding window rather than a hash
+/// that only started accumulating at `min_size`.
+const CDC_WARMUP_WINDOW: usize = 48;
+
+/// Scans `data` for a FastCDC content-defined boundary and returns the cut length.
+/// Never returns less than `min_size` or more than `max_size` (both clamped to `data.len()`).
+fn cdc_cut_point(data: &[u8], params: &CdcParams) -> usize
+{
+    let max_size = params.max_size.min(data.len());
+    if max_size <= params.min_size
+    {
+        return max_size;
+    }
+
+    let bits = (params.avg_size.max(1) as f64).log2().round() as u32;
+    let mask_s = cdc_mask(bits + 2);
+    let mask_l = cdc_mask(bits.saturating_sub(2));
+
+    let mut hash: u64 = 0;
+    let warmup_start = params.min_size.saturating_sub(CDC_WARMUP_WINDOW);
+    for &byte in &data[warmup_start..params.min_size]
+    {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+    }
+
+    let mut i = params.min_size;
+    while i < max_size
+    {
+        hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+        let mask = if i < params.avg_size { mask_s } else { mask_l };
+        if hash & mask == 0
+        {
+            return i + 1;
+        }
+        i += 1;
+    }
+    max_size
+}
+
+pub fn encode_stream_cdc(data: &[u8], options: EncodeOptions, params: CdcParams) -> Result<Vec<u8>, DSError>
+{
+    let mut result: BytesMut = BytesMut::new();
+    let mut window: Vec<u8> = Vec::new();
+    let mut rest = data;
+
+    while !rest.is_empty()
+    {
+        let cut = cdc_cut_point(rest, &params);
+        let buffer = &rest[..cut];
+        let dict: Option<&[u8]> = if options.linked && !window.is_empty() { Some(&window) } else { None };
+        write_chunk(&mut result, buffer, options.checksum, dict);
+        if options.linked
+        {
+            window.extend_from_slice(buffer);
+            if window.len() > LINK_WINDOW
+            {
+                let excess = window.len() - LINK_WINDOW;
+                window.drain(0..excess);
+            }
+        }
+        rest = &rest[cut..];
+    }
+    Ok(result.to_vec())
+}
+
+/// Standard FastCDC Gear table: 256 pseudo-random 64-bit constants, one per input byte value.
+const GEAR: [u64; 256] = [
+    0x1c948e1575796814, 0xae9ef1ab67004bdb, 0x7a2988d31f16e86e, 0x7a5daea24eba3ba7,
+    0xbb83c0c2207ad3e6, 0xe2da71d9f0e79e32, 0xf037b46f16a54449, 0xafd7e49c4512ee8c,
+    0x25ade43f8dcffc85, 0x0028cf578ec6bd94, 0x9f26b835468010bb, 0xb9792de59de179e6,
+    0xca030ef931c393c6, 0x34c690fbf80367a9, 0x5bddd920e3712b45, 0x7587183f9ed6c5bf,
+    0xac39bb1f2aa2a8fc, 0xee1f1c282cdf78cc, 0xee912e80c0b0b0d3, 0x0149fc107d224ebb,
+    0xb7173f0e17ddd8fb, 0x0818f93aaafefbec, 0xb7b727cad1bcac49, 0x0f27c615267daafc,
+    0x627e5846e66e1cdc, 0x896c34fcd5c143d5, 0xd86261f86fb4d030, 0x34277192202efa4b,
+    0xe86163428d79cc4c, 0xcc80491077821e40, 0xd5a79428c5380876, 0x46bb59954a664517,
+    0xd615b473ae917cd1, 0xada6b9c1aaa299c0, 0x18be433d79d1001c, 0x7d42902e01e03d3f,
+    0xc336ea240cc55a28, 0x2a6e0c08500e8148, 0x97add580a62a5e9f, 0x21a10a7bd4fb549c,
+    0xbd61e521ddaf5e0b, 0x369e55e09758f5ab, 0xd6bd449915fc5db6, 0xe0ebb372a27d4e0b,
+    0xe881ff7db53ab26e, 0xb295815c0ad9d50c, 0x29748cec736e65fa, 0x029d4d575b392925,
+    0x7b5d52485e89f7ce, 0x4a77b5797e686207, 0x3b54bafa59f120bb, 0x48c5e171d53dcc93,
+    0x8e2a8538b38c614d, 0x9f7a4f5ad14729ed, 0x2100412c2323cfea, 0x61ec9c0d6fe30a13,
+    0xe7718fb33904e4c5, 0xca2008b9acc9ef40, 0xa251e94fc57aa676, 0x263240c61c50d933,
+    0x46d8f93ef7577dd6, 0x9479417daccdff6e, 0x5b52165400bd7942, 0x8151ad860e24e2bf,
+    0xe82de5d9052182c7, 0x97a0a2276751ddd1, 0xc84303a82db39c9c, 0xe8718e5547f4865d,
+    0x6788c3dabfc84451, 0xb81df11f951178a2, 0xa872f4fbadc968e8, 0x0f3acead1a0605e9,
+    0x5888fada257031c6, 0x8674fbbbea0b4bc8, 0x55aaa61acead6f7c, 0x56b3cb62382f0f8b,
+    0x347125003d5d8155, 0x932ee7fe3a28b65e, 0x5aec7b1b833a65de, 0x037672637d06f303,
+    0xf1f08e4d292ba51b, 0x5ed39e20cce85599, 0x27f6a93cc0dd9a73, 0x2fb423e0ff31be46,
+    0x04671eb1f06f9c8d, 0x08d6b838ff1ccb41, 0xdae7598073fdcbd2, 0x2167f5e688770662,
+    0xcf4cdb49ecdde32d, 0x669abb2445da919c, 0x96aef901debb4ca7, 0x48c6f03856a5b723,
+    0xcf6a0b80f476d289, 0x62568d960a1668c2, 0xa2c64b0494dce97f, 0x601ecb1b34fad593,
+    0x1c07a82ef3679f73, 0xbe9f9bfef7c92a49, 0x6c61e7193c8f6a7f, 0xfd956bbc800ab564,
+    0x8aa6044c5433707e, 0xdf326685cec950f3, 0x9e5b32cc5b43ae70, 0xccf73827f611d8f4,
+    0x360406225e60d817, 0x87e4a17414abad4d, 0x7ed02d9b2ad3100c, 0xeea05398243753c2,
+    0x41572d3175a6fc7e, 0xf4f73fb0d9380fa7, 0x65c661fb62669e18, 0xe47cf521b0a505e1,
+    0xe4207ef3449d0910, 0x5a504cbd12174279, 0x71bbced8e97d5df8, 0x1a537ef2b248c955,
+    0x4171d1d41857db2b, 0xfe5b86ddf65935e6, 0x28ae9e9d7ab065c6, 0x644a5f1e62bf9be3,
+    0xa90b7026cd2f1120, 0xb7c6eab3abf40f3b, 0xd7769e29a9239ac3, 0x8ba64b6e1e80f0b6,
+    0xff4083fba4de3f85, 0x680fd6d835870118, 0xcac2be8c8833aed4, 0xd1a01eeba6d37400,
+    0x5577099a6ec5a999, 0xcb137103ebe3ffd0, 0xdc25c5ad2b944524, 0xd9e27631efa8699c,
+    0x686a053001656f59, 0x3263342ed0865172, 0xa49508ce83eaee7b, 0x53a831d8db6b1f1f,
+    0x25f7077ba004eab9, 0xaef1e66bd8ebfd28, 0x868e17aa682cfd0a, 0x3bd0093ca994a5ca,
+    0x135cdb946e507857, 0x0a912e0be93b662d, 0xd8ecc4441007c8c1, 0x561e178466b59252,
+    0x2def8ed2bee575f5, 0x1e1e09f42a457db7, 0x8ec320b9f8cee28c, 0xd759f8f74596cf14,
+    0xfab0ac026cefeea9, 0xf049455bd5f7abba, 0xed9e9412382777fc, 0x8b1203c0a21cc318,
+    0x673bc8068db2cbbd, 0x4300b1abbe595484, 0x7878934971175b02, 0x9cfad36b194da5f4,
+    0xd9970769a636154c, 0xb1f94fcd55922bd5, 0x7c0ea01c2cb45b2b, 0x9971d632d8ee10d1,
+    0x26c82af59fec8b8f, 0x15b8ae154495021a, 0x9a2672445c041a0d, 0x8b357230d0fac6b0,
+    0x0a04c3630d2dd796, 0x921266f124a1ee12, 0xff63189c118357f3, 0xb25e46b109239319,
+    0x08d842320598fc51, 0x1eb7bfa516e9c70d, 0xe29b365d9851fba1, 0x57c138a082ef0741,
+    0x8d3a94d42bc7d7bd, 0xf96e62b9f980add1, 0xf5402a5f2b5a8660, 0x44d4f5cbfb1b56b5,
+    0x141c60550a57a2a7, 0x642bec2ac328dc00, 0xb1c896615f0d8c0b, 0xa2e086fb081d1960,
+    0x6619754e04dfd33c, 0x13a0b00dbdd67818, 0xcd8e62fbc8729760, 0x283eec042ed5b63b,
+    0xa3efd3c7d1905547, 0xf1a02042408553de, 0xb9ee414e7168be7e, 0x34c2866da01009ef,
+    0x9583e6772652607b, 0x158c7ea5fde901db, 0x7acada6411a4a929, 0x853f8cd012e531ba,
+    0x72553849906ad830, 0x7bb792c2e8bc87fd, 0x5cd9a5a6c9cbdbab, 0xc99d409981d0e564,
+    0x69bc17221fd380f4, 0x61442302a22539a8, 0xd074b99d3a4cf99d, 0x987b6f273b2ae50c,
+    0x3fe733cead818809, 0x8db44f415b71437a, 0x7b753867ee8047fe, 0x6637a45f4301c6f3,
+    0x2e6f055a34d9f81f, 0x244c958624f5385a, 0xdc99a194adcbfa5d, 0xfb63a3fafc53f503,
+    0xd3b003d84cf0a1df, 0x419ae704975ec587, 0x4dbc42ecd43865f6, 0xd78c5568e81ecd88,
+    0x8a8120c194710aee, 0x5b336727063e2449, 0x00a9b547dd35420a, 0x4c5c2fd3bbbfbc52,
+    0xf78c616a48a6b8f2, 0xf903e17b91e445dd, 0x48431681b5b2e979, 0xee3314082bb774f9,
+    0x08405a9dc6d83118, 0xbaa2863a8e403efe, 0x83446cd8b0435298, 0x16c6f534009baea8,
+    0xd4d88ba0f66c4ed6, 0x1e765b9cec74b6c7, 0xfdbff1bac7029b8f, 0xbf8cb457d89b670a,
+    0x2642a944eaf70ab8, 0x4e042ea096602653, 0xf76f87e65aa480b4, 0x8c7af60091fcb7d1,
+    0x981c27559bb9199d, 0x51e575de83ddc0f2, 0x3926f3d015c99f33, 0x4ed8c3da363ed7ed,
+    0x07171a1066a58a83, 0x8630c5d201125e14, 0x61c846eafc217344, 0xa943aae763132c1f,
+    0xc2c5c9821a867af3, 0x839f8cb73b93074d, 0xe8267a4b417e5bec, 0xbf989cda1062e827,
+    0x6529cefa105723ee, 0xe86e14386eecfd0d, 0xb40375f2ffe7bdca, 0xe060479440d55fe4,
+    0x58b0a43eb7563058, 0xdb0224fbaec22b7f, 0x9b8c29d1647c680f, 0xa62ce73446a8812e,
+    0x43fa52d40917dc4f, 0x7fab5556671c4fd4, 0xe509d926d2917b19, 0x9680a9fa10c5c35d,
+];
+
+/// Max bytes in a LEB128-encoded u64: ceil(64 / 7) = 10.
+const VARINT_MAX_BYTES: u32 = 10;
+
+fn varint_overflow_error() -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, "varint overflow")
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64>
+{
+    let mut result: u64 = 0;
+    let mut shift: u32 = 0;
+    loop {
+        if shift / 7 >= VARINT_MAX_BYTES
+        {
+            return Err(varint_overflow_error());
+        }
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        result |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0
+        {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+/// Like `read_varint`, but returns `Ok(None)` if the reader is cleanly at EOF before any byte is read.
+fn read_varint_opt<R: Read>(reader: &mut R) -> io::Result<Option<u64>>
+{
+    let mut byte = [0u8; 1];
+    if reader.read(&mut byte)? == 0
+    {
+        return Ok(None);
+    }
+    let mut result = (byte[0] & 0x7f) as u64;
+    let mut shift: u32 = 0;
+    while byte[0] & 0x80 != 0
+    {
+        shift += 7;
+        if shift / 7 >= VARINT_MAX_BYTES
+        {
+            return Err(varint_overflow_error());
+        }
+        reader.read_exact(&mut byte)?;
+        result |= ((byte[0] & 0x7f) as u64) << shift;
+    }
+    Ok(Some(result))
+}
+
+/// Buffers writes up to `BLOCKSIZE` and emits them as chunks, so a caller can encode
+/// arbitrarily large input through a `Write` sink with bounded memory.
+///
+/// Like `BufWriter`, the buffered tail is flushed on drop; prefer calling `into_inner()`
+/// or `flush()` explicitly so a failure to emit the final chunk isn't silently swallowed.
+pub struct StreamEncoder<W: Write>
+{
+    writer: Option<W>,
+    buffer: Vec<u8>,
+    window: Vec<u8>,
+    options: EncodeOptions,
+}
+
+impl<W: Write> Drop for StreamEncoder<W>
+{
+    fn drop(&mut self)
+    {
+        let _ = self.emit_chunk();
+    }
+}
+
+impl<W: Write> StreamEncoder<W>
+{
+    pub fn new(writer: W) -> Self
+    {
+        Self::with_options(writer, EncodeOptions::default())
+    }
+
+    pub fn with_options(writer: W, options: EncodeOptions) -> Self
+    {
+        StreamEncoder { writer: Some(writer), buffer: Vec::with_capacity(BLOCKSIZE), window: Vec::new(), options }
+    }
+
+    fn emit_chunk(&mut self) -> io::Result<()>
+    {
+        if self.buffer.is_empty()
+        {
+            return Ok(());
+        }
+        let dict: Option<&[u8]> = if self.options.linked && !self.window.is_empty() { Some(&self.window) } else { None };
+        let mut chunk = BytesMut::new();
+        write_chunk(&mut chunk, &self.buffer, self.options.checksum, dict);
+        // `writer` is only `None` after `into_inner` has taken it, at which point
+        // `buffer` is always empty (see above), so this unwrap never fires.
+        self.writer.as_mut().expect("StreamEncoder used after into_inner").write_all(&chunk)?;
+        if self.options.linked
+        {
+            self.window.extend_from_slice(&self.buffer);
+            if self.window.len() > LINK_WINDOW
+            {
+                let excess = self.window.len() - LINK_WINDOW;
+                self.window.drain(0..excess);
+            }
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Flushes any buffered tail chunk and returns the wrapped writer.
+    pub fn into_inner(mut self) -> io::Result<W>
+    {
+        self.emit_chunk()?;
+        Ok(self.writer.take().expect("writer taken twice"))
+    }
+}
+
+impl<W: Write> Write for StreamEncoder<W>
+{
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize>
+    {
+        let total = buf.len();
+        while !buf.is_empty()
+        {
+            let space = BLOCKSIZE - self.buffer.len();
+            let take = space.min(buf.len());
+            self.buffer.extend_from_slice(&buf[..take]);
+            buf = &buf[take..];
+            if self.buffer.len() == BLOCKSIZE
+            {
+                self.emit_chunk()?;
+            }
+        }
+        Ok(total)
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.emit_chunk()?;
+        self.writer.as_mut().expect("StreamEncoder used after into_inner").flush()
+    }
+}
+
+/// Pulls one chunk at a time from a `Read` source and serves decompressed bytes from an
+/// internal buffer, so a caller can decode arbitrarily large input with bounded memory.
+pub struct StreamDecoder<R: Read>
+{
+    reader: R,
+    pending: Vec<u8>,
+    pending_pos: usize,
+    window: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: Read> StreamDecoder<R>
+{
+    pub fn new(reader: R) -> Self
+    {
+        StreamDecoder { reader, pending: Vec::new(), pending_pos: 0, window: Vec::new(), finished: false }
+    }
+
+    fn pull_chunk(&mut self) -> io::Result<()>
+    {
+        let flags_raw = match read_varint_opt(&mut self.reader)? {
+            Some(v) => v as u32,
+            None => { self.finished = true; return Ok(()); }
+        };
+        let flags: ChunkFlags = ChunkFlags::from_bits_retain(flags_raw);
+        let is_compressed = flags.contains(ChunkFlags::Compressed);
+        let original_length = read_varint(&mut self.reader)? as usize;
+        let length: usize =
+            if is_compressed
+            {
+                read_varint(&mut self.reader)? as usize
+            } else {
+                original_length
+            };
+        let expected_checksum: Option<u32> =
+            if flags.contains(ChunkFlags::Checksum)
+            {
+                let mut raw = [0u8; 4];
+                self.reader.read_exact(&mut raw)?;
+                Some(u32::from_be_bytes(raw))
+            } else {
+                None
+            };
+        let mut payload = vec![0u8; length];
+        self.reader.read_exact(&mut payload)?;
+        if let Some(expected) = expected_checksum
+        {
+            let actual = chunk_checksum(&payload);
+            if actual != expected
+            {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("chunk checksum mismatch: expected {expected:#x}, actual {actual:#x}")));
+            }
+        }
+        let mut out = vec![0u8; original_length];
+        if is_compressed
+        {
+            let written =
+                if flags.contains(ChunkFlags::Passes)
+                {
+                    lz4_flex::block::decompress_into_with_dict(&payload, &mut out, &self.window)
+                } else {
+                    lz4_flex::block::decompress_into(&payload, &mut out)
+                }.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if written != original_length
+            {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "decompressed length mismatch"));
+            }
+        }
+        else
+        {
+            out.copy_from_slice(&payload);
+        }
+        self.window.extend_from_slice(&out);
+        if self.window.len() > LINK_WINDOW
+        {
+            let excess = self.window.len() - LINK_WINDOW;
+            self.window.drain(0..excess);
+        }
+        self.pending = out;
+        self.pending_pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StreamDecoder<R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+        while self.pending_pos >= self.pending.len() && !self.finished
+        {
+            self.pull_chunk()?;
+        }
+        let available = &self.pending[self.pending_pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pending_pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn sample(len: usize) -> Vec<u8>
+    {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn checksum_round_trip()
+    {
+        let input = sample(2_500_000);
+        let mut buf = Cursor::new(input.as_slice());
+        let encoded = encode_stream_with_options(&mut buf, EncodeOptions { checksum: true, linked: false }).unwrap();
+        let mut encoded2 = encoded.clone();
+        let decoded = decode_stream(Cursor::new(&mut encoded2), input.len() + 1).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn linked_round_trip()
+    {
+        let input = sample(3_000_000);
+        let mut buf = Cursor::new(input.as_slice());
+        let encoded = encode_stream_with_options(&mut buf, EncodeOptions { checksum: false, linked: true }).unwrap();
+        let mut encoded2 = encoded.clone();
+        let decoded = decode_stream(Cursor::new(&mut encoded2), input.len() + 1).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_detected()
+    {
+        let input = sample(10_000);
+        let mut buf = Cursor::new(input.as_slice());
+        let mut encoded = encode_stream_with_options(&mut buf, EncodeOptions { checksum: true, linked: false }).unwrap();
+        // Flip the last byte of the compressed payload without touching any header field.
+        let corrupt_at = encoded.len() - 1;
+        encoded[corrupt_at] ^= 0xff;
+        let err = decode_stream(Cursor::new(&mut encoded), input.len() + 1).unwrap_err();
+        assert!(matches!(err, DSError::ChecksumMismatch { .. }));
+    }
+
+    #[test]
+    fn decode_range_on_truncated_input_errors_instead_of_panicking()
+    {
+        let input = sample(2_500_000);
+        let mut buf = Cursor::new(input.as_slice());
+        let encoded = encode_stream_with_options(&mut buf, EncodeOptions { checksum: true, linked: false }).unwrap();
+        let mut encoded2 = encoded.clone();
+        let index = build_index(Cursor::new(&mut encoded2)).unwrap();
+
+        // Cut the stream a couple of bytes into the second chunk's header.
+        let truncate_at = index.entries[1].input_pos + 2;
+        let mut truncated = encoded;
+        truncated.truncate(truncate_at);
+
+        let result = decode_range(Cursor::new(&mut truncated), &index, 0..input.len());
+        assert!(result.is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_round_trip()
+    {
+        let input = sample(4_500_000);
+        let encoded = encode_stream_parallel(&input, 4).unwrap();
+        let mut encoded2 = encoded.clone();
+        let decoded = decode_stream_parallel(Cursor::new(&mut encoded2), input.len() + 1, 4).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_decode_rejects_linked_chunks()
+    {
+        let input = sample(2_500_000);
+        let mut buf = Cursor::new(input.as_slice());
+        let encoded = encode_stream_with_options(&mut buf, EncodeOptions { checksum: false, linked: true }).unwrap();
+        let mut encoded2 = encoded.clone();
+        let err = decode_stream_parallel(Cursor::new(&mut encoded2), input.len() + 1, 4).unwrap_err();
+        assert!(matches!(err, DSError::LinkedChunkUnsupported));
+    }
+
+    #[test]
+    fn cdc_round_trip()
+    {
+        let input = sample(500_000);
+        let params = CdcParams { min_size: 4 * 1024, avg_size: 16 * 1024, max_size: 64 * 1024 };
+        let encoded = encode_stream_cdc(&input, EncodeOptions::default(), params).unwrap();
+        let mut encoded2 = encoded.clone();
+        let decoded = decode_stream(Cursor::new(&mut encoded2), input.len() + 1).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn cdc_small_input_is_a_single_chunk()
+    {
+        let input = sample(100);
+        let params = CdcParams::default();
+        assert_eq!(cdc_cut_point(&input, &params), input.len());
+    }
+
+    #[test]
+    fn cdc_cut_point_respects_min_and_max_size()
+    {
+        let input = sample(10_000);
+        let params = CdcParams { min_size: 1_000, avg_size: 2_000, max_size: 3_000 };
+        let cut = cdc_cut_point(&input, &params);
+        assert!(cut >= params.min_size && cut <= params.max_size);
+    }
+
+    #[test]
+    fn stream_encoder_decoder_round_trip()
+    {
+        let input = sample(2_500_000);
+        let mut sink: Vec<u8> = Vec::new();
+        {
+            let mut encoder = StreamEncoder::with_options(&mut sink, EncodeOptions { checksum: true, linked: true });
+            // Write in small, uneven pieces to exercise the internal buffering logic.
+            for piece in input.chunks(4096)
+            {
+                encoder.write_all(piece).unwrap();
+            }
+            encoder.into_inner().unwrap();
+        }
+
+        let mut decoder = StreamDecoder::new(sink.as_slice());
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn stream_decoder_on_truncated_input_errors_instead_of_panicking()
+    {
+        let input = sample(2_500_000);
+        let mut sink: Vec<u8> = Vec::new();
+        {
+            let mut encoder = StreamEncoder::new(&mut sink);
+            encoder.write_all(&input).unwrap();
+            encoder.into_inner().unwrap();
+        }
+        sink.truncate(sink.len() - 2);
+
+        let mut decoder = StreamDecoder::new(sink.as_slice());
+        let mut decoded = Vec::new();
+        assert!(decoder.read_to_end(&mut decoded).is_err());
+    }
+}